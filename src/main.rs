@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs::{create_dir_all, read_dir, read_to_string, OpenOptions};
 use std::io::Write;
@@ -36,6 +37,10 @@ enum Command {
         #[arg(long)]
         release: bool,
 
+        /// Build with a named profile from `chp.toml`. Takes precedence over --release.
+        #[arg(long)]
+        profile: Option<String>,
+
         /// Any argument passed after this flag is passed to your program.
         #[arg(value_parser, short, num_args = 1.., value_delimiter = ' ')]
         args: Vec<String>,
@@ -46,6 +51,46 @@ enum Command {
         /// The release flag enables the release profile (uses debug profile by default).
         #[arg(long)]
         release: bool,
+
+        /// Build with a named profile from `chp.toml`. Takes precedence over --release.
+        #[arg(long)]
+        profile: Option<String>,
+    },
+
+    /// Add a git dependency to chp.toml.
+    Add {
+        /// The name the dependency is referred to as, and the directory it is vendored into.
+        name: String,
+
+        /// The git URL to clone the dependency from.
+        #[arg(long)]
+        git: Option<String>,
+
+        /// Pin the dependency to a specific commit.
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Pin the dependency to a specific tag.
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Pin the dependency to a specific branch.
+        #[arg(long)]
+        branch: Option<String>,
+    },
+
+    /// Fetch every dependency declared in chp.toml as a git submodule under vendor/.
+    Install,
+
+    /// Generate a compile_commands.json compilation database for clangd/IDE tooling.
+    Generate {
+        /// The release flag generates the database for the release profile (debug by default).
+        #[arg(long)]
+        release: bool,
+
+        /// Generate for a named profile from `chp.toml`. Takes precedence over --release.
+        #[arg(long)]
+        profile: Option<String>,
     },
 }
 
@@ -57,23 +102,69 @@ fn main() -> Result<()> {
     match cli.command {
         Command::Init => init_project(None),
         Command::New { name } => init_project(Some(name)),
-        Command::Run { release, args } => run(release, args),
-        Command::Build { release } => build(release),
+        Command::Run {
+            release,
+            profile,
+            args,
+        } => run(resolve_profile_name(profile, release), args),
+        Command::Build { release, profile } => {
+            build(resolve_profile_name(profile, release)).map(|_| ())
+        }
+        Command::Add {
+            name,
+            git,
+            rev,
+            tag,
+            branch,
+        } => add_dependency(name, git, rev, tag, branch),
+        Command::Install => install_dependencies(),
+        Command::Generate { release, profile } => generate(resolve_profile_name(profile, release)),
     }
 }
 
+/// `--profile` always wins; `--release` is sugar for `--profile release`, and the
+/// absence of both falls back to the `debug` profile.
+fn resolve_profile_name(profile: Option<String>, release: bool) -> String {
+    profile.unwrap_or_else(|| if release { "release" } else { "debug" }.to_owned())
+}
+
 #[derive(Deserialize)]
 struct Config {
     name: String,
     command: String,
     compile_cpp_in_dirs: Option<Vec<String>>,
-    profiles: Profiles,
+    profiles: HashMap<String, Vec<String>>,
+    dependencies: Option<HashMap<String, Dependency>>,
+
+    /// Shell commands run, in order, from the project root before compiling.
+    pre_build: Option<Vec<String>>,
+
+    /// Shell commands run, in order, from the project root after a successful build.
+    post_build: Option<Vec<String>>,
+
+    /// Shell commands run, in order, from the project root before `chp run` builds.
+    pre_run: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
-struct Profiles {
-    debug: Vec<String>,
-    release: Vec<String>,
+struct Dependency {
+    git: String,
+    rev: Option<String>,
+    tag: Option<String>,
+    branch: Option<String>,
+
+    /// Directory inside the dependency, relative to its vendored root, that holds
+    /// its public headers. Injected as `-Ivendor/<name>/<include>`.
+    #[serde(default = "default_dependency_include")]
+    include: String,
+
+    /// Directory inside the dependency, relative to its vendored root, that is
+    /// scanned for `.cpp` sources to compile alongside the project's own.
+    source: Option<String>,
+}
+
+fn default_dependency_include() -> String {
+    "include".to_owned()
 }
 
 fn find_root() -> Result<PathBuf> {
@@ -140,52 +231,368 @@ fn find_cpp_files_in_dirs_helper(
     Ok(())
 }
 
-fn build(release: bool) -> Result<()> {
-    let current_dir = current_dir()?;
+/// Resolves everything a build needs: the compiler, the root directory, the
+/// selected profile, the list of translation units (project + dependencies)
+/// and the two groups of flags (dependency includes, then profile flags)
+/// that get passed for each.
+struct BuildPlan {
+    root: PathBuf,
+    name: String,
+    command: String,
+    profile: String,
+    cpp_files: Vec<PathBuf>,
+    include_args: Vec<String>,
+    profile_args: Vec<String>,
+    pre_build: Vec<String>,
+    post_build: Vec<String>,
+}
+
+fn prepare_build(profile: String) -> Result<BuildPlan> {
+    let root = find_root()?;
     let Config {
+        name,
         command,
         compile_cpp_in_dirs,
-        profiles,
+        mut profiles,
+        dependencies,
+        pre_build,
+        post_build,
         ..
     } = read_config()?;
-    let args = if release {
-        profiles.release
-    } else {
-        profiles.debug
-    };
+    let profile_args = profiles
+        .remove(&profile)
+        .ok_or_else(|| Report::msg(format!("No profile named {profile:?} in chp.toml")))?;
+
+    let mut cpp_files = find_cpp_files_in_dirs(compile_cpp_in_dirs)?;
+    let mut include_args = Vec::new();
 
-    println!("Building {:?}", &current_dir);
+    for (dep_name, dependency) in dependencies.unwrap_or_default() {
+        include_args.push(format!("-Ivendor/{dep_name}/{}", dependency.include));
 
-    let output = TerminalCommand::new(command)
-        .args(find_cpp_files_in_dirs(compile_cpp_in_dirs)?)
-        .args(args)
+        if let Some(source) = dependency.source {
+            let dep_source_dir = format!("vendor/{dep_name}/{source}");
+            if root.join(&dep_source_dir).is_dir() {
+                cpp_files.extend(find_cpp_files_in_dirs(Some(vec![dep_source_dir]))?);
+            }
+        }
+    }
+
+    Ok(BuildPlan {
+        root,
+        name,
+        command,
+        profile,
+        cpp_files,
+        include_args,
+        profile_args,
+        pre_build: pre_build.unwrap_or_default(),
+        post_build: post_build.unwrap_or_default(),
+    })
+}
+
+/// The binary `build()` will produce: the `-o` value from the profile's own
+/// args if it set one (the source of truth), or `build/<profile>/<name>` with
+/// the platform's executable suffix otherwise. `run()` uses this same path,
+/// so the two can never disagree about where the binary landed.
+fn resolve_output_path(plan: &BuildPlan) -> PathBuf {
+    parse_output_flag(&plan.profile_args)
+        .map(|raw| plan.root.join(raw))
+        .unwrap_or_else(|| {
+            plan.root.join("build").join(&plan.profile).join(format!(
+                "{}{}",
+                plan.name,
+                std::env::consts::EXE_SUFFIX
+            ))
+        })
+}
+
+fn parse_output_flag(args: &[String]) -> Option<PathBuf> {
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        if arg == "-o" {
+            return iter.next().map(PathBuf::from);
+        }
+    }
+
+    None
+}
+
+fn build(profile: String) -> Result<PathBuf> {
+    let current_dir = current_dir()?;
+    let plan = prepare_build(profile)?;
+    let output_path = resolve_output_path(&plan);
+
+    println!("Building {:?} ({})", &current_dir, &plan.profile);
+
+    run_hooks(&plan.pre_build, &plan.root)?;
+
+    write_compile_commands(&plan)?;
+
+    let mut obj_dir = plan.root.clone();
+    obj_dir.push("build");
+    obj_dir.push(&plan.profile);
+    obj_dir.push("obj");
+
+    let search_dirs = include_search_dirs(&plan);
+    let compile_args = strip_output_flag(&plan.profile_args);
+
+    let mut object_files = Vec::new();
+
+    for cpp_file in &plan.cpp_files {
+        let source_path = plan.root.join(cpp_file);
+        let obj_path = PathBuf::from(format!("{}.o", obj_dir.join(cpp_file).display()));
+
+        if let Some(parent) = obj_path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        if needs_recompile(&source_path, &obj_path, &search_dirs)? {
+            println!("Compiling {cpp_file:?}");
+
+            let output = TerminalCommand::new(&plan.command)
+                .current_dir(&plan.root)
+                .arg("-c")
+                .arg(&source_path)
+                .args(&plan.include_args)
+                .args(&compile_args)
+                .arg("-o")
+                .arg(&obj_path)
+                .output()?;
+
+            if !output.stderr.is_empty() {
+                std::io::stderr().write_all(&output.stderr)?;
+            }
+
+            if !output.status.success() {
+                return Err(Report::msg(format!("Failed to compile {cpp_file:?}")));
+            }
+        }
+
+        object_files.push(obj_path);
+    }
+
+    if let Some(parent) = output_path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut link_args = plan.profile_args.clone();
+    if parse_output_flag(&plan.profile_args).is_none() {
+        link_args.push("-o".to_owned());
+        link_args.push(output_path.display().to_string());
+    }
+
+    let output = TerminalCommand::new(&plan.command)
+        .current_dir(&plan.root)
+        .args(&object_files)
+        .args(&link_args)
         .output()?;
 
     if !output.stderr.is_empty() {
         std::io::stderr().write_all(&output.stderr)?;
-        return Ok(());
+    }
+
+    if !output.status.success() {
+        return Err(Report::msg(format!("Failed to link {:?}", &output_path)));
+    }
+
+    run_hooks(&plan.post_build, &plan.root)?;
+
+    Ok(output_path)
+}
+
+/// Runs each hook, in order, as a shell command from `root`. A non-zero exit
+/// aborts with its captured stderr surfaced as the returned error.
+fn run_hooks(hooks: &[String], root: &Path) -> Result<()> {
+    for hook in hooks {
+        println!("Running hook: {hook}");
+
+        let output = TerminalCommand::new("sh")
+            .arg("-c")
+            .arg(hook)
+            .current_dir(root)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Report::msg(format!(
+                "Hook `{hook}` failed:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
     }
 
     Ok(())
 }
 
-fn run(release: bool, args: Vec<String>) -> Result<()> {
-    build(release)?;
+/// Directories local `#include "..."` resolution should search, besides a header's
+/// own directory: the project root, and every `-I` directory we pass the compiler.
+fn include_search_dirs(plan: &BuildPlan) -> Vec<PathBuf> {
+    let mut dirs = vec![plan.root.clone()];
 
-    let mut current_dir = current_dir()?;
-    let config = read_config()?;
+    for include_arg in plan.include_args.iter().chain(&plan.profile_args) {
+        if let Some(dir) = include_arg.strip_prefix("-I") {
+            dirs.push(plan.root.join(dir));
+        }
+    }
+
+    dirs
+}
+
+/// Drops a profile's `-o <path>` pair, since per-object compilation picks its own
+/// output path and the final link step re-adds the profile's `-o` unmodified.
+fn strip_output_flag(args: &[String]) -> Vec<String> {
+    let mut result = Vec::with_capacity(args.len());
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        if arg == "-o" {
+            iter.next();
+            continue;
+        }
+        result.push(arg.clone());
+    }
+
+    result
+}
+
+/// An object file only needs rebuilding if it is missing, or older than its
+/// source file or any header the source transitively `#include`s locally.
+/// The object file's own mtime doubles as the cache; nothing else is stored.
+fn needs_recompile(source: &Path, obj: &Path, search_dirs: &[PathBuf]) -> Result<bool> {
+    let obj_mtime = match std::fs::metadata(obj).and_then(|meta| meta.modified()) {
+        Ok(mtime) => mtime,
+        Err(_) => return Ok(true),
+    };
+
+    for input in collect_transitive_includes(source, search_dirs)? {
+        if std::fs::metadata(&input)?.modified()? > obj_mtime {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Parses `#include "..."` lines in `source` and every header it reaches,
+/// resolving each either relative to the including file or to `search_dirs`.
+/// System headers (`#include <...>`) are intentionally ignored.
+fn collect_transitive_includes(source: &Path, search_dirs: &[PathBuf]) -> Result<HashSet<PathBuf>> {
+    let mut visited = HashSet::new();
+    let mut queue = vec![source.to_path_buf()];
+
+    while let Some(file) = queue.pop() {
+        if !visited.insert(file.clone()) {
+            continue;
+        }
+
+        let Ok(content) = read_to_string(&file) else {
+            continue;
+        };
+
+        for line in content.lines() {
+            let Some(rest) = line.trim().strip_prefix("#include \"") else {
+                continue;
+            };
+            let Some(end) = rest.find('"') else {
+                continue;
+            };
+
+            if let Some(resolved) = resolve_local_include(&file, &rest[..end], search_dirs) {
+                queue.push(resolved);
+            }
+        }
+    }
+
+    Ok(visited)
+}
+
+fn resolve_local_include(from: &Path, included: &str, search_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if let Some(parent) = from.parent() {
+        let candidate = parent.join(included);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+    }
+
+    search_dirs
+        .iter()
+        .map(|dir| dir.join(included))
+        .find(|candidate| candidate.is_file())
+}
+
+fn generate(profile: String) -> Result<()> {
+    let plan = prepare_build(profile)?;
 
-    current_dir.push("build");
-    if release {
-        current_dir.push("release");
-    } else {
-        current_dir.push("debug");
+    write_compile_commands(&plan)?;
+
+    println!("Wrote {:?}", plan.root.join("compile_commands.json"));
+
+    Ok(())
+}
+
+/// Writes a `compile_commands.json` compilation database at the project root,
+/// with one entry per translation unit reflecting exactly the arguments
+/// `build()` would pass it, so clangd and friends stay in sync with `chp.toml`.
+fn write_compile_commands(plan: &BuildPlan) -> Result<()> {
+    let mut entries = Vec::new();
+    let compile_args = strip_output_flag(&plan.profile_args);
+    let obj_dir = plan.root.join("build").join(&plan.profile).join("obj");
+
+    for file in &plan.cpp_files {
+        let mut arguments = vec![
+            plan.command.clone(),
+            "-c".to_owned(),
+            plan.root.join(file).display().to_string(),
+        ];
+        arguments.extend(plan.include_args.iter().cloned());
+        arguments.extend(compile_args.iter().cloned());
+        arguments.push("-o".to_owned());
+        arguments.push(format!("{}.o", obj_dir.join(file).display()));
+
+        let arguments_json = arguments
+            .iter()
+            .map(|arg| format!("\"{}\"", json_escape(arg)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        entries.push(format!(
+            "  {{\n    \"directory\": \"{}\",\n    \"file\": \"{}\",\n    \"arguments\": [{}]\n  }}",
+            json_escape(&plan.root.display().to_string()),
+            json_escape(&file.display().to_string()),
+            arguments_json
+        ));
     }
-    current_dir.push(format!("{}.exe", config.name));
 
-    println!("Running {:?}", &current_dir);
+    let content = format!("[\n{}\n]\n", entries.join(",\n"));
+
+    let mut compile_commands_path = plan.root.clone();
+    compile_commands_path.push("compile_commands.json");
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(compile_commands_path)?;
+    file.write_all(content.as_bytes())?;
 
-    let output = TerminalCommand::new(current_dir).args(args).output()?;
+    Ok(())
+}
+
+fn json_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn run(profile: String, args: Vec<String>) -> Result<()> {
+    let root = find_root()?;
+    let mut config = read_config()?;
+
+    run_hooks(&config.pre_run.take().unwrap_or_default(), &root)?;
+
+    let output_path = build(profile)?;
+
+    println!("Running {:?}", &output_path);
+
+    let output = TerminalCommand::new(output_path).args(args).output()?;
 
     std::io::stdout().write_all(&output.stdout)?;
     std::io::stderr().write_all(&output.stderr)?;
@@ -193,6 +600,150 @@ fn run(release: bool, args: Vec<String>) -> Result<()> {
     Ok(())
 }
 
+fn add_dependency(
+    name: String,
+    git: Option<String>,
+    rev: Option<String>,
+    tag: Option<String>,
+    branch: Option<String>,
+) -> Result<()> {
+    let git = git.ok_or_else(|| Report::msg("`--git` is required to add a dependency"))?;
+
+    let mut chp_path = find_root()?;
+    chp_path.push("chp.toml");
+
+    let content = read_to_string(&chp_path)?;
+    let mut document: toml::Value = toml::from_str(&content)?;
+
+    let mut dependency = toml::map::Map::new();
+    dependency.insert("git".to_owned(), toml::Value::String(git));
+    if let Some(rev) = rev {
+        dependency.insert("rev".to_owned(), toml::Value::String(rev));
+    }
+    if let Some(tag) = tag {
+        dependency.insert("tag".to_owned(), toml::Value::String(tag));
+    }
+    if let Some(branch) = branch {
+        dependency.insert("branch".to_owned(), toml::Value::String(branch));
+    }
+
+    let dependencies = document
+        .as_table_mut()
+        .ok_or_else(|| Report::msg("chp.toml is not a valid TOML document"))?
+        .entry("dependencies")
+        .or_insert_with(|| toml::Value::Table(toml::map::Map::new()));
+
+    // Inserting under the same key overwrites a previous `chp add` for this
+    // dependency instead of emitting a second, now-duplicate table header.
+    dependencies
+        .as_table_mut()
+        .ok_or_else(|| Report::msg("`dependencies` in chp.toml is not a table"))?
+        .insert(name.clone(), toml::Value::Table(dependency));
+
+    let mut config_file = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(&chp_path)?;
+    config_file.write_all(toml::to_string_pretty(&document)?.as_bytes())?;
+
+    println!("Added dependency {name:?} to chp.toml");
+
+    Ok(())
+}
+
+fn install_dependencies() -> Result<()> {
+    let root = find_root()?;
+    let config = read_config()?;
+
+    let Some(dependencies) = config.dependencies else {
+        println!("No dependencies declared in chp.toml");
+        return Ok(());
+    };
+
+    let mut vendor_dir = root.clone();
+    vendor_dir.push("vendor");
+    create_dir_all(&vendor_dir)?;
+
+    for (name, dependency) in dependencies {
+        let dep_path = Path::new("vendor").join(&name);
+
+        println!("Installing dependency {name:?} from {:?}", dependency.git);
+
+        let output = TerminalCommand::new("git")
+            .args(["submodule", "add", "--force", &dependency.git])
+            .arg(&dep_path)
+            .current_dir(&root)
+            .output()?;
+
+        if !output.stderr.is_empty() {
+            std::io::stderr().write_all(&output.stderr)?;
+        }
+
+        if !output.status.success() {
+            return Err(Report::msg(format!(
+                "Failed to install dependency {name:?}: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        if let Some(reference) = dependency
+            .rev
+            .as_ref()
+            .or(dependency.tag.as_ref())
+            .or(dependency.branch.as_ref())
+        {
+            let output = TerminalCommand::new("git")
+                .args(["checkout", reference])
+                .current_dir(root.join(&dep_path))
+                .output()?;
+
+            if !output.stderr.is_empty() {
+                std::io::stderr().write_all(&output.stderr)?;
+            }
+
+            if !output.status.success() {
+                return Err(Report::msg(format!(
+                    "Failed to check out {reference:?} for dependency {name:?}: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                )));
+            }
+
+            // Record the pinned commit in the superproject, so the submodule
+            // update below keeps it instead of resetting to the added HEAD.
+            let output = TerminalCommand::new("git")
+                .arg("add")
+                .arg(&dep_path)
+                .current_dir(&root)
+                .output()?;
+
+            if !output.status.success() {
+                return Err(Report::msg(format!(
+                    "Failed to record {reference:?} for dependency {name:?}: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                )));
+            }
+        }
+    }
+
+    let output = TerminalCommand::new("git")
+        .args(["submodule", "update", "--init", "--recursive"])
+        .current_dir(&root)
+        .output()?;
+
+    if !output.stderr.is_empty() {
+        std::io::stderr().write_all(&output.stderr)?;
+    }
+
+    if !output.status.success() {
+        return Err(Report::msg(format!(
+            "Failed to update git submodules: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(())
+}
+
 fn init_project(maybe_name: Option<String>) -> Result<()> {
     let mut current_dir = current_dir()?;
 
@@ -237,8 +788,12 @@ fn write_project(mut path: PathBuf) -> Result<()> {
     // Create chp configuration TOML file.
     path.push("chp.toml");
     {
+        let config_content = CONFIG_FILE_CONTENT
+            .replace("{}", &project_name)
+            .replace("{exe}", std::env::consts::EXE_SUFFIX);
+
         let mut config_file = OpenOptions::new().create(true).write(true).open(&path)?;
-        config_file.write_all(CONFIG_FILE_CONTENT.replace("{}", &project_name).as_bytes())?;
+        config_file.write_all(config_content.as_bytes())?;
     }
     path.pop();
 
@@ -296,7 +851,7 @@ debug = [
     "-Og", 
     "-g", 
     "-o", 
-    "build/debug/{}.exe",
+    "build/debug/{}{exe}",
 ]
 release = [
     # All cpp files found in the directories provided in the 
@@ -311,7 +866,7 @@ release = [
     "-fconcepts", 
     "-O2", 
     "-o", 
-    "build/release/{}.exe",
+    "build/release/{}{exe}",
 ]
 "#;
 const MAIN_FILE_CONTENT: &str = r#"#include <iostream>